@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nostr_sdk::PublicKey;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::services::MultiMintService;
+
+/// Rolling window a per-client budget is accounted against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetWindow {
+    Daily,
+    Monthly,
+}
+
+impl BudgetWindow {
+    /// Length of the window in seconds. Months are treated as a rolling 30-day
+    /// period rather than a calendar month to keep rollover arithmetic O(1).
+    const fn period_secs(self) -> u64 {
+        match self {
+            BudgetWindow::Daily => 24 * 60 * 60,
+            BudgetWindow::Monthly => 30 * 24 * 60 * 60,
+        }
+    }
+
+    /// Start of the window containing `now`, aligned to the epoch.
+    fn window_start(self, now_secs: u64) -> u64 {
+        now_secs - (now_secs % self.period_secs())
+    }
+
+    /// Stable string used as part of the database key.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BudgetWindow::Daily => "daily",
+            BudgetWindow::Monthly => "monthly",
+        }
+    }
+
+    /// Parses a window name, accepting `daily` or `monthly`.
+    pub fn parse(s: &str) -> Result<Self, anyhow::Error> {
+        match s {
+            "daily" => Ok(BudgetWindow::Daily),
+            "monthly" => Ok(BudgetWindow::Monthly),
+            other => Err(anyhow::anyhow!("unknown budget window: {other}")),
+        }
+    }
+}
+
+/// Spend limits for a single connected client.
+#[derive(Debug, Clone)]
+pub struct ConnectionLimit {
+    /// Maximum value of any single payment, in millisatoshis.
+    pub max_amount: u64,
+    /// Window the budget rolls over on.
+    pub window: BudgetWindow,
+    /// Total spend allowed within one window, in millisatoshis.
+    pub window_limit: u64,
+}
+
+/// Per-connection limits, with a fallback applied to any client without an
+/// explicit entry.
+///
+/// Keying is by the request author pubkey. Under the current single shared
+/// connection key (see [`crate::managers::KeyManager`]) every client presents
+/// the same pubkey, so `per_connection` overrides only take effect once the
+/// daemon issues distinct per-connection secrets; today the `default` limit
+/// acts as a single global budget.
+#[derive(Debug, Clone)]
+pub struct BudgetConfig {
+    pub per_connection: HashMap<PublicKey, ConnectionLimit>,
+    pub default: ConnectionLimit,
+}
+
+impl BudgetConfig {
+    fn limit_for(&self, pubkey: &PublicKey) -> &ConnectionLimit {
+        self.per_connection.get(pubkey).unwrap_or(&self.default)
+    }
+}
+
+/// Durable spend counter for a `(pubkey, window)` pair. A single row is kept
+/// per client and rolled over in place when the period boundary passes, rather
+/// than storing one row per transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetRecord {
+    /// Epoch-aligned start of the window the counter currently tracks.
+    pub window_start_secs: u64,
+    /// Amount spent so far in the current window, in millisatoshis.
+    pub spent_msats: u64,
+}
+
+#[derive(Debug, Error)]
+pub enum BudgetError {
+    #[error("payment of {amount} msats exceeds the per-payment limit of {max} msats")]
+    AmountTooLarge { amount: u64, max: u64 },
+    #[error("payment of {amount} msats would exceed the remaining budget of {remaining} msats")]
+    QuotaExceeded { amount: u64, remaining: u64 },
+}
+
+/// Persists per-client spending budgets in the database backing
+/// [`MultiMintService`], enforcing both a per-payment cap and a windowed total
+/// with atomic read-modify-write on each payment.
+#[derive(Clone)]
+pub struct BudgetManager {
+    multimint: MultiMintService,
+    config: Arc<BudgetConfig>,
+    /// Serializes read-modify-write so concurrent payments can't race the
+    /// persisted counter.
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl BudgetManager {
+    pub fn new(multimint: MultiMintService, config: BudgetConfig) -> Self {
+        Self {
+            multimint,
+            config: Arc::new(config),
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Checks a pending payment against the client's limits and, if allowed,
+    /// durably reserves it. Returns the budget remaining after the spend.
+    pub async fn check_and_reserve(
+        &self,
+        pubkey: &PublicKey,
+        amount: u64,
+    ) -> Result<u64, anyhow::Error> {
+        let limit = self.config.limit_for(pubkey).clone();
+        if amount > limit.max_amount {
+            return Err(BudgetError::AmountTooLarge {
+                amount,
+                max: limit.max_amount,
+            }
+            .into());
+        }
+
+        let _guard = self.write_lock.lock().await;
+        let now = now_secs();
+        let start = limit.window.window_start(now);
+
+        let mut record = self
+            .multimint
+            .load_budget(pubkey, limit.window)
+            .await?
+            .filter(|r| r.window_start_secs == start)
+            .unwrap_or(BudgetRecord {
+                window_start_secs: start,
+                spent_msats: 0,
+            });
+
+        let remaining = limit.window_limit.saturating_sub(record.spent_msats);
+        if amount > remaining {
+            return Err(BudgetError::QuotaExceeded { amount, remaining }.into());
+        }
+
+        record.spent_msats += amount;
+        self.multimint
+            .store_budget(pubkey, limit.window, &record)
+            .await?;
+        Ok(limit.window_limit.saturating_sub(record.spent_msats))
+    }
+
+    /// Refunds a previously reserved amount when the payment ultimately fails,
+    /// so an errored spend doesn't permanently burn the client's budget. The
+    /// counter is only decremented within the window the reservation was made
+    /// in; a refund arriving after a rollover is dropped.
+    pub async fn release(&self, pubkey: &PublicKey, amount: u64) -> Result<(), anyhow::Error> {
+        let window = self.config.limit_for(pubkey).window;
+        let _guard = self.write_lock.lock().await;
+        let start = window.window_start(now_secs());
+
+        let Some(mut record) = self
+            .multimint
+            .load_budget(pubkey, window)
+            .await?
+            .filter(|r| r.window_start_secs == start)
+        else {
+            return Ok(());
+        };
+
+        record.spent_msats = record.spent_msats.saturating_sub(amount);
+        self.multimint.store_budget(pubkey, window, &record).await?;
+        Ok(())
+    }
+
+    /// Budget remaining in the current window for `pubkey`, for reporting in
+    /// `get_balance`/`get_info` responses.
+    pub async fn remaining(&self, pubkey: &PublicKey) -> Result<u64, anyhow::Error> {
+        let limit = self.config.limit_for(pubkey);
+        let start = limit.window.window_start(now_secs());
+        let spent = self
+            .multimint
+            .load_budget(pubkey, limit.window)
+            .await?
+            .filter(|r| r.window_start_secs == start)
+            .map(|r| r.spent_msats)
+            .unwrap_or(0);
+        Ok(limit.window_limit.saturating_sub(spent))
+    }
+}
+
+impl std::fmt::Debug for BudgetManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BudgetManager")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+use clap::Parser;
+use nostr_sdk::PublicKey;
+use tracing::warn;
+
+use crate::budget::{BudgetConfig, BudgetWindow, ConnectionLimit};
+
+/// Command line / environment configuration for the nwc daemon.
+#[derive(Debug, Clone, Parser)]
+#[command(author, version, about)]
+pub struct Cli {
+    /// Path to the JSON file holding the connection and service keypairs.
+    #[arg(long, env = "FEDIMINT_NWC_KEYS_FILE", default_value = "keys.json")]
+    pub keys_file: PathBuf,
+
+    /// Path to the database backing the multimint client.
+    #[arg(long, env = "FEDIMINT_NWC_DB_PATH", default_value = "fedimint_nwc.db")]
+    pub db_path: PathBuf,
+
+    /// Relays to connect to, as `wss://` urls.
+    #[arg(long, env = "FEDIMINT_NWC_RELAYS", value_delimiter = ',')]
+    pub relays: Vec<String>,
+
+    /// Federation invite code used on first run.
+    #[arg(long, env = "FEDIMINT_NWC_INVITE_CODE")]
+    pub invite_code: String,
+
+    /// Optional manual secret for deterministic client recovery.
+    #[arg(long, env = "FEDIMINT_NWC_MANUAL_SECRET")]
+    pub manual_secret: Option<String>,
+
+    /// Maximum value of a single payment, in millisatoshis.
+    #[arg(long, env = "FEDIMINT_NWC_MAX_AMOUNT", default_value_t = 100_000_000)]
+    pub max_amount: u64,
+
+    /// Total spend allowed per default budget window (see `budget_window`), in
+    /// millisatoshis.
+    #[arg(long, env = "FEDIMINT_NWC_DAILY_LIMIT", default_value_t = 1_000_000_000)]
+    pub daily_limit: u64,
+
+    /// Requests allowed per client pubkey per minute.
+    #[arg(
+        long,
+        env = "FEDIMINT_NWC_RATE_LIMIT_PER_MINUTE",
+        default_value = "60"
+    )]
+    pub rate_limit_per_minute: NonZeroU32,
+
+    /// Additional burst of requests a client may spend at once.
+    #[arg(long, env = "FEDIMINT_NWC_RATE_LIMIT_BURST", default_value = "10")]
+    pub rate_limit_burst: NonZeroU32,
+
+    /// Address the Prometheus metrics endpoint listens on.
+    #[arg(
+        long,
+        env = "FEDIMINT_NWC_METRICS_ADDR",
+        default_value = "127.0.0.1:9000"
+    )]
+    pub metrics_addr: SocketAddr,
+
+    /// How often, in seconds, to probe relay connectivity and reconnect.
+    #[arg(
+        long,
+        env = "FEDIMINT_NWC_CONNECTIVITY_CHECK_SECS",
+        default_value_t = 30
+    )]
+    pub connectivity_check_secs: u64,
+
+    /// Maximum number of requests processed concurrently.
+    #[arg(long, env = "FEDIMINT_NWC_MAX_IN_FLIGHT", default_value_t = 16)]
+    pub max_in_flight: usize,
+
+    /// Window the default spending budget rolls over on.
+    #[arg(long, env = "FEDIMINT_NWC_BUDGET_WINDOW", default_value = "daily")]
+    pub budget_window: String,
+
+    /// Per-connection budget overrides, each formatted as
+    /// `<pubkey>:<daily|monthly>:<window_limit_msats>:<max_amount_msats>`.
+    #[arg(long = "budget", env = "FEDIMINT_NWC_BUDGETS", value_delimiter = ',')]
+    pub budgets: Vec<String>,
+}
+
+impl Cli {
+    /// Builds the [`BudgetConfig`] from the global limits and any per-connection
+    /// overrides, falling back to `daily` for an unrecognised window.
+    pub fn budget_config(&self) -> BudgetConfig {
+        let default = ConnectionLimit {
+            max_amount: self.max_amount,
+            window: BudgetWindow::parse(&self.budget_window).unwrap_or(BudgetWindow::Daily),
+            window_limit: self.daily_limit,
+        };
+
+        let mut per_connection = HashMap::new();
+        for entry in &self.budgets {
+            match parse_budget_override(entry) {
+                Ok((pubkey, limit)) => {
+                    per_connection.insert(pubkey, limit);
+                }
+                Err(e) => warn!("Ignoring malformed budget override '{entry}': {e}"),
+            }
+        }
+
+        BudgetConfig {
+            per_connection,
+            default,
+        }
+    }
+}
+
+fn parse_budget_override(entry: &str) -> Result<(PublicKey, ConnectionLimit), anyhow::Error> {
+    let parts: Vec<&str> = entry.split(':').collect();
+    let [pubkey, window, window_limit, max_amount] = parts.as_slice() else {
+        anyhow::bail!("expected <pubkey>:<window>:<window_limit>:<max_amount>");
+    };
+    Ok((
+        PublicKey::parse(pubkey)?,
+        ConnectionLimit {
+            max_amount: max_amount.parse()?,
+            window: BudgetWindow::parse(window)?,
+            window_limit: window_limit.parse()?,
+        },
+    ))
+}
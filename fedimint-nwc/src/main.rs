@@ -1,25 +1,38 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
 use anyhow::Result;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
 use clap::Parser;
 use nostr_sdk::{JsonUtil, Kind, RelayPoolNotification};
+use prometheus::{Encoder, TextEncoder};
 use tokio::pin;
 use tracing::{error, info};
 
+pub mod budget;
 pub mod config;
 pub mod managers;
+pub mod notifications;
 pub mod nwc;
 pub mod services;
 pub mod state;
 
-use state::AppState;
+use state::{AppState, Metrics};
 
 use crate::config::Cli;
 
-#[tokio::main]
+#[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
     dotenv::dotenv().ok();
 
     let cli = Cli::parse();
+    let metrics_addr = cli.metrics_addr;
+    let connectivity_check_interval = Duration::from_secs(cli.connectivity_check_secs);
     let state = AppState::new(cli).await?;
 
     // Connect to the relay pool and broadcast the info event on startup
@@ -28,19 +41,55 @@ async fn main() -> Result<()> {
         .nostr_service
         .broadcast_info_event(&state.key_manager)
         .await?;
+    state.nostr_service.subscribe(&state.key_manager).await?;
+
+    // Serve Prometheus metrics on a side listener so operators can scrape
+    // throughput and failure counters while the event loop runs.
+    tokio::spawn(serve_metrics(metrics_addr, state.metrics.clone()));
 
     // Start the event loop
-    event_loop(state.clone()).await?;
+    event_loop(state.clone(), connectivity_check_interval).await?;
 
     Ok(())
 }
 
+/// Serves the Prometheus registry in text format on `GET /metrics`.
+async fn serve_metrics(addr: SocketAddr, metrics: Metrics) {
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(metrics);
+    match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => {
+            info!("Serving metrics on {addr}");
+            if let Err(e) = axum::serve(listener, app).await {
+                error!("Metrics server error: {e}");
+            }
+        }
+        Err(e) => error!("Failed to bind metrics listener on {addr}: {e}"),
+    }
+}
+
+async fn metrics_handler(State(metrics): State<Metrics>) -> impl IntoResponse {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    if let Err(e) = encoder.encode(&metrics.registry.gather(), &mut buffer) {
+        error!("Failed to encode metrics: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, Vec::new()).into_response();
+    }
+    ([(header::CONTENT_TYPE, encoder.format_type())], buffer).into_response()
+}
+
 /// Event loop that listens for nostr wallet connect events and handles them
-async fn event_loop(state: AppState) -> Result<()> {
+async fn event_loop(state: AppState, connectivity_check_interval: Duration) -> Result<()> {
     // Handle ctrl+c to gracefully shutdown the event loop
     let ctrl_c = tokio::signal::ctrl_c();
     pin!(ctrl_c);
 
+    // Watchdog that periodically probes relay connectivity so a silently
+    // dropped relay gets reconnected instead of stalling the daemon.
+    let mut connectivity_check = tokio::time::interval(connectivity_check_interval);
+    connectivity_check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
     let mut notifications = state.nostr_service.notifications();
     info!("Listening for events...");
     loop {
@@ -74,6 +123,17 @@ async fn event_loop(state: AppState) -> Result<()> {
                     },
                     Err(_) => {},
                 }
+            },
+            _ = connectivity_check.tick() => {
+                // Reconnect any dropped relays and re-announce ourselves so
+                // clients can keep reaching the wallet after an outage.
+                if let Err(e) = state
+                    .nostr_service
+                    .check_connectivity(&state.key_manager)
+                    .await
+                {
+                    error!("Connectivity check failed: {e}");
+                }
             }
         }
     }
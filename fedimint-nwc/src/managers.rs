@@ -0,0 +1,56 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use nostr_sdk::Keys;
+use serde::{Deserialize, Serialize};
+
+/// Holds the keypairs the daemon operates with: the shared connection keypair
+/// clients authenticate requests with, and the service keypair used to sign the
+/// NIP-47 info event.
+#[derive(Debug, Clone)]
+pub struct KeyManager {
+    user_keys: Keys,
+    server_keys: Keys,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredKeys {
+    user_secret: String,
+    server_secret: String,
+}
+
+impl KeyManager {
+    /// Loads the keypairs from `keys_file`, generating and persisting a fresh
+    /// pair the first time the daemon runs.
+    pub fn new(keys_file: &Path) -> Result<Self> {
+        let stored = if keys_file.exists() {
+            let contents = fs::read_to_string(keys_file)
+                .with_context(|| format!("reading keys file {}", keys_file.display()))?;
+            serde_json::from_str(&contents)?
+        } else {
+            let stored = StoredKeys {
+                user_secret: Keys::generate().secret_key().to_secret_hex(),
+                server_secret: Keys::generate().secret_key().to_secret_hex(),
+            };
+            fs::write(keys_file, serde_json::to_string_pretty(&stored)?)
+                .with_context(|| format!("writing keys file {}", keys_file.display()))?;
+            stored
+        };
+
+        Ok(Self {
+            user_keys: Keys::parse(&stored.user_secret)?,
+            server_keys: Keys::parse(&stored.server_secret)?,
+        })
+    }
+
+    /// The shared connection keypair; client requests are authored with it.
+    pub fn user_keys(&self) -> &Keys {
+        &self.user_keys
+    }
+
+    /// The service keypair used to sign the info event.
+    pub fn server_keys(&self) -> &Keys {
+        &self.server_keys
+    }
+}
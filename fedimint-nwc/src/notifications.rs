@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// Legacy NIP-47 notification event kind (encrypted with NIP-04).
+pub const NOTIFICATION_KIND: u16 = 23196;
+/// NIP-47 notification event kind (encrypted with NIP-44).
+pub const NIP44_NOTIFICATION_KIND: u16 = 23197;
+
+/// A wallet activity notification pushed to a connected client, following the
+/// NIP-47 notifications extension. Serialized as `{ "notification_type", ... }`
+/// into the encrypted event content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "notification_type", content = "notification", rename_all = "snake_case")]
+pub enum WalletNotification {
+    PaymentReceived(PaymentNotification),
+    PaymentSent(PaymentNotification),
+}
+
+impl WalletNotification {
+    /// The `notification_type` string advertised in the info event and carried
+    /// in the notification payload.
+    pub fn notification_type(&self) -> &'static str {
+        match self {
+            WalletNotification::PaymentReceived(_) => "payment_received",
+            WalletNotification::PaymentSent(_) => "payment_sent",
+        }
+    }
+}
+
+/// Payload shared by `payment_received` and `payment_sent` notifications.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentNotification {
+    /// Payment hash of the settled invoice, hex encoded.
+    pub payment_hash: String,
+    /// Settled amount in millisatoshis.
+    pub amount: u64,
+    /// The bolt11 invoice, when the payment was a lightning transfer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice: Option<String>,
+}
@@ -0,0 +1,222 @@
+use anyhow::{anyhow, Result};
+use lightning_invoice::Bolt11Invoice;
+use nostr_sdk::nips::nip04;
+use nostr_sdk::Event;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::state::AppState;
+
+/// Rate-limit configuration threaded into the request limiter.
+///
+/// Spend limits (`max_amount`, `daily_limit`) are enforced by
+/// [`crate::budget::BudgetManager`], configured from the `Cli` through
+/// [`crate::config::Cli::budget_config`].
+#[derive(Debug, Clone)]
+pub struct NwcConfig {
+    pub rate_limit_per_minute: std::num::NonZeroU32,
+    pub rate_limit_burst: std::num::NonZeroU32,
+}
+
+/// NIP-47 error codes returned to the client in a response's `error` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    RateLimited,
+    QuotaExceeded,
+    NotImplemented,
+    InsufficientBalance,
+    PaymentFailed,
+    Restricted,
+    Unauthorized,
+    Internal,
+    Other,
+}
+
+impl ErrorCode {
+    /// The wire string carried in the `error.code` field.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::QuotaExceeded => "QUOTA_EXCEEDED",
+            ErrorCode::NotImplemented => "NOT_IMPLEMENTED",
+            ErrorCode::InsufficientBalance => "INSUFFICIENT_BALANCE",
+            ErrorCode::PaymentFailed => "PAYMENT_FAILED",
+            ErrorCode::Restricted => "RESTRICTED",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::Internal => "INTERNAL",
+            ErrorCode::Other => "OTHER",
+        }
+    }
+}
+
+/// NIP-47 request methods the daemon understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    PayInvoice,
+    MultiPayInvoice,
+    PayKeysend,
+    MakeInvoice,
+    LookupInvoice,
+    GetBalance,
+    GetInfo,
+    Unknown,
+}
+
+impl Method {
+    /// Label used for logs and the Prometheus `method` dimension.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::PayInvoice => "pay_invoice",
+            Method::MultiPayInvoice => "multi_pay_invoice",
+            Method::PayKeysend => "pay_keysend",
+            Method::MakeInvoice => "make_invoice",
+            Method::LookupInvoice => "lookup_invoice",
+            Method::GetBalance => "get_balance",
+            Method::GetInfo => "get_info",
+            Method::Unknown => "unknown",
+        }
+    }
+
+    fn parse(method: &str) -> Self {
+        match method {
+            "pay_invoice" => Method::PayInvoice,
+            "multi_pay_invoice" => Method::MultiPayInvoice,
+            "pay_keysend" => Method::PayKeysend,
+            "make_invoice" => Method::MakeInvoice,
+            "lookup_invoice" => Method::LookupInvoice,
+            "get_balance" => Method::GetBalance,
+            "get_info" => Method::GetInfo,
+            _ => Method::Unknown,
+        }
+    }
+}
+
+/// A decrypted NIP-47 request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// Decrypts and parses the request carried in `event`. The shared NIP-04
+/// secret is derived from the service key and the requesting client pubkey.
+pub fn parse_request(state: &AppState, event: &Event) -> Result<Request> {
+    let secret = state.key_manager.server_keys().secret_key();
+    let content = nip04::decrypt(secret, &event.pubkey, &event.content)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Best-effort method extraction for metrics labeling; never fails so the
+/// `method` dimension is populated even for malformed or rejected requests.
+pub fn request_method(state: &AppState, event: &Event) -> Method {
+    parse_request(state, event)
+        .map(|r| Method::parse(&r.method))
+        .unwrap_or(Method::Unknown)
+}
+
+/// Handles a single verified NIP-47 request end to end: decrypt, dispatch, and
+/// reply to the client with the encrypted response event.
+pub async fn handle_nwc_request(state: &AppState, event: Event) -> Result<()> {
+    let request = parse_request(state, &event)?;
+    let method = Method::parse(&request.method);
+    info!("Handling {} request", method.as_str());
+
+    let result = match method {
+        Method::GetBalance => {
+            // Report the remaining budget alongside the balance so wallets can
+            // display how much of the client's allowance is left.
+            let balance = state.multimint_service.get_balance().await;
+            let remaining = state.budget.remaining(&event.pubkey).await?;
+            balance.map(|msats| json!({ "balance": msats, "budget": remaining }))
+        }
+        Method::GetInfo => {
+            let remaining = state.budget.remaining(&event.pubkey).await?;
+            let mut info = state.multimint_service.get_info();
+            info["budget"] = json!(remaining);
+            Ok(info)
+        }
+        Method::PayInvoice => {
+            let invoice = request
+                .params
+                .get("invoice")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("missing invoice param"))?;
+
+            // Enforce the per-payment cap and the windowed budget before
+            // spending, atomically reserving the amount; a compromised client
+            // cannot exceed its allowance even across restarts.
+            let amount = invoice
+                .parse::<Bolt11Invoice>()?
+                .amount_milli_satoshis()
+                .ok_or_else(|| anyhow!("amountless invoices are not supported"))?;
+            if let Err(e) = state.budget.check_and_reserve(&event.pubkey, amount).await {
+                return state
+                    .nostr_service
+                    .send_error_response(
+                        &state.key_manager,
+                        &event,
+                        ErrorCode::QuotaExceeded,
+                        &e.to_string(),
+                    )
+                    .await;
+            }
+
+            match state.multimint_service.pay_invoice(invoice).await {
+                Ok(preimage) => Ok(json!({ "preimage": preimage })),
+                Err(e) => {
+                    // No sats left the wallet, so refund the reserved budget
+                    // rather than permanently burning it on a failed payment.
+                    if let Err(re) = state.budget.release(&event.pubkey, amount).await {
+                        error!("Failed to refund budget after failed payment: {re}");
+                    }
+                    Err(e)
+                }
+            }
+        }
+        Method::MakeInvoice => {
+            let amount = request
+                .params
+                .get("amount")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow!("missing amount param"))?;
+            state
+                .multimint_service
+                .make_invoice(amount)
+                .await
+                .map(|invoice| json!({ "invoice": invoice }))
+        }
+        _ => {
+            return state
+                .nostr_service
+                .send_error_response(
+                    &state.key_manager,
+                    &event,
+                    ErrorCode::NotImplemented,
+                    "method not supported",
+                )
+                .await;
+        }
+    };
+
+    match result {
+        Ok(value) => {
+            state
+                .nostr_service
+                .send_response(&state.key_manager, &event, method, value)
+                .await
+        }
+        Err(e) => {
+            state
+                .nostr_service
+                .send_error_response(
+                    &state.key_manager,
+                    &event,
+                    ErrorCode::Internal,
+                    &e.to_string(),
+                )
+                .await
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod multimint;
+mod nostr;
+
+pub use multimint::MultiMintService;
+pub use nostr::NostrService;
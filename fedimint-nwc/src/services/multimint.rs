@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use fedimint_core::core::OperationId;
+use fedimint_core::db::{Database, IDatabaseTransactionOpsCore};
+use fedimint_core::module::registry::ModuleRegistry;
+use fedimint_rocksdb::RocksDb;
+use lightning_invoice::Bolt11Invoice;
+use multimint::MultiMint;
+use nostr_sdk::PublicKey;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::budget::{BudgetRecord, BudgetWindow};
+use crate::notifications::{PaymentNotification, WalletNotification};
+
+/// Capacity of the wallet activity broadcast channel.
+const ACTIVITY_CHANNEL_CAP: usize = 1024;
+
+/// Wraps the fedimint multimint client the daemon pays and receives with.
+#[derive(Debug, Clone)]
+pub struct MultiMintService {
+    multimint: MultiMint,
+    /// Durable store for per-client spending budgets, alongside the multimint
+    /// database.
+    db: Database,
+    /// Publishes ecash/lightning receive and spend events to notification
+    /// subscribers.
+    activity_tx: broadcast::Sender<WalletNotification>,
+}
+
+impl MultiMintService {
+    pub async fn new(db_path: PathBuf) -> Result<Self> {
+        let multimint = MultiMint::new(db_path.clone()).await?;
+        let db = Database::new(
+            RocksDb::open(db_path.join("nwc_budgets"))?,
+            ModuleRegistry::default(),
+        );
+        let (activity_tx, _) = broadcast::channel(ACTIVITY_CHANNEL_CAP);
+        Ok(Self {
+            multimint,
+            db,
+            activity_tx,
+        })
+    }
+
+    /// Loads the persisted budget counter for `(pubkey, window)`, if any.
+    pub async fn load_budget(
+        &self,
+        pubkey: &PublicKey,
+        window: BudgetWindow,
+    ) -> Result<Option<BudgetRecord>> {
+        let mut dbtx = self.db.begin_transaction().await;
+        let bytes = dbtx.raw_get_bytes(&budget_key(pubkey, window)).await?;
+        Ok(bytes.map(|b| serde_json::from_slice(&b)).transpose()?)
+    }
+
+    /// Atomically persists the updated budget counter for `(pubkey, window)`.
+    pub async fn store_budget(
+        &self,
+        pubkey: &PublicKey,
+        window: BudgetWindow,
+        record: &BudgetRecord,
+    ) -> Result<()> {
+        let mut dbtx = self.db.begin_transaction().await;
+        dbtx.raw_insert_bytes(&budget_key(pubkey, window), &serde_json::to_vec(record)?)
+            .await?;
+        dbtx.commit_tx().await;
+        Ok(())
+    }
+
+    /// Subscribes to the stream of wallet activity used to drive outgoing
+    /// NIP-47 notifications.
+    pub fn subscribe_activity(&self) -> broadcast::Receiver<WalletNotification> {
+        self.activity_tx.subscribe()
+    }
+
+    /// Publishes a received-payment event; invoked by the federation operation
+    /// listener when an invoice the daemon issued settles.
+    pub fn publish_received(&self, amount: u64, payment_hash: String) {
+        // A send error only means there are no subscribers yet; that is fine.
+        let _ = self.activity_tx.send(WalletNotification::PaymentReceived(
+            PaymentNotification {
+                payment_hash,
+                amount,
+                invoice: None,
+            },
+        ));
+    }
+
+    /// Joins the federation on first run.
+    pub async fn init_multimint(
+        &self,
+        invite_code: &str,
+        manual_secret: Option<String>,
+    ) -> Result<()> {
+        self.multimint
+            .register_new(invite_code.parse()?, manual_secret)
+            .await?;
+        Ok(())
+    }
+
+    /// Total spendable balance across joined federations, in millisatoshis.
+    pub async fn get_balance(&self) -> Result<u64> {
+        Ok(self.multimint.ecash_balance().await?)
+    }
+
+    /// Static wallet metadata returned by `get_info`.
+    pub fn get_info(&self) -> Value {
+        json!({
+            "alias": "fedimint-nwc",
+            "methods": ["pay_invoice", "make_invoice", "get_balance", "get_info"],
+        })
+    }
+
+    /// Pays a bolt11 invoice and returns the payment preimage, publishing a
+    /// sent-payment notification to subscribers on success.
+    pub async fn pay_invoice(&self, invoice: &str) -> Result<String> {
+        let bolt11: Bolt11Invoice = invoice.parse()?;
+        let preimage = self.multimint.pay_invoice(bolt11.clone()).await?;
+        let _ = self
+            .activity_tx
+            .send(WalletNotification::PaymentSent(PaymentNotification {
+                payment_hash: bolt11.payment_hash().to_string(),
+                amount: bolt11.amount_milli_satoshis().unwrap_or(0),
+                invoice: Some(invoice.to_string()),
+            }));
+        Ok(preimage)
+    }
+
+    /// Creates a bolt11 invoice for `amount` millisatoshis and spawns a
+    /// listener that emits a `payment_received` notification once the invoice
+    /// settles in the federation.
+    pub async fn make_invoice(&self, amount: u64) -> Result<String> {
+        let (operation_id, invoice) = self.multimint.make_invoice(amount).await?;
+        self.spawn_receive_listener(operation_id, amount, invoice.payment_hash().to_string());
+        Ok(invoice.to_string())
+    }
+
+    /// Awaits settlement of an issued invoice and publishes the received-payment
+    /// event, so clients are notified of inbound ecash/lightning without
+    /// polling.
+    fn spawn_receive_listener(
+        &self,
+        operation_id: OperationId,
+        amount: u64,
+        payment_hash: String,
+    ) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            match service.multimint.await_receive(operation_id).await {
+                Ok(()) => service.publish_received(amount, payment_hash),
+                Err(e) => warn!("Await receive for {operation_id:?} failed: {e}"),
+            }
+        });
+    }
+}
+
+/// Database key for a `(pubkey, window)` budget counter.
+fn budget_key(pubkey: &PublicKey, window: BudgetWindow) -> Vec<u8> {
+    format!("nwc_budget/{}/{}", pubkey, window.as_str()).into_bytes()
+}
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use nostr_sdk::nips::nip04;
+use nostr_sdk::{
+    Client, Event, EventBuilder, Filter, Kind, RelayPoolNotification, RelayStatus, Tag,
+};
+use serde_json::json;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::managers::KeyManager;
+use crate::notifications::{WalletNotification, NOTIFICATION_KIND};
+use crate::nwc::{ErrorCode, Method};
+
+/// Methods advertised in the NIP-47 info event.
+const SUPPORTED_METHODS: &str =
+    "pay_invoice make_invoice get_balance get_info";
+
+/// Notification types advertised alongside the supported methods.
+const SUPPORTED_NOTIFICATIONS: &str = "payment_received payment_sent";
+
+/// Thin wrapper over the nostr client used to talk to the relay pool.
+#[derive(Debug, Clone)]
+pub struct NostrService {
+    client: Client,
+    relays: Vec<String>,
+}
+
+impl NostrService {
+    pub async fn new(key_manager: &KeyManager, relays: &[String]) -> Result<Self> {
+        let client = Client::new(key_manager.server_keys());
+        for relay in relays {
+            client.add_relay(relay).await?;
+        }
+        Ok(Self {
+            client,
+            relays: relays.to_vec(),
+        })
+    }
+
+    pub async fn connect(&self) {
+        self.client.connect().await;
+    }
+
+    pub async fn disconnect(&self) -> Result<()> {
+        self.client.disconnect().await?;
+        Ok(())
+    }
+
+    pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
+        self.client.notifications()
+    }
+
+    /// Subscribes to the wallet-connect request filter so the daemon receives
+    /// NIP-47 requests addressed to it.
+    pub async fn subscribe(&self, key_manager: &KeyManager) -> Result<()> {
+        let filter = Filter::new()
+            .kind(Kind::WalletConnectRequest)
+            .author(key_manager.user_keys().public_key());
+        self.client.subscribe(vec![filter], None).await?;
+        Ok(())
+    }
+
+    /// Probes each relay and, for any that have silently dropped, reconnects,
+    /// re-broadcasts the info event, and re-subscribes the wallet-connect
+    /// filter so no requests are missed once the relay is back.
+    pub async fn check_connectivity(&self, key_manager: &KeyManager) -> Result<()> {
+        let mut recovered = false;
+        for (url, relay) in self.client.relays().await {
+            if relay.status().await != RelayStatus::Connected {
+                warn!("Relay {url} disconnected, attempting reconnect");
+                if let Err(e) = self.client.connect_relay(&url).await {
+                    warn!("Failed to reconnect to {url}: {e}");
+                    continue;
+                }
+                recovered = true;
+            }
+        }
+
+        if recovered {
+            self.broadcast_info_event(key_manager).await?;
+            self.subscribe(key_manager).await?;
+            info!("Recovered relay connectivity and re-subscribed");
+        }
+        Ok(())
+    }
+
+    /// Publishes the kind-13194 info event advertising the wallet's
+    /// capabilities so clients know which methods are available. The
+    /// `notifications` tag advertises the NIP-47 notifications extension so
+    /// compliant wallets subscribe to our notification events.
+    pub async fn broadcast_info_event(&self, key_manager: &KeyManager) -> Result<()> {
+        let event = EventBuilder::new(
+            Kind::WalletConnectInfo,
+            SUPPORTED_METHODS,
+            [Tag::custom(
+                nostr_sdk::TagKind::custom("notifications"),
+                [SUPPORTED_NOTIFICATIONS],
+            )],
+        )
+        .to_event(key_manager.server_keys())?;
+        self.client.send_event(event).await?;
+        info!("Broadcast NIP-47 info event");
+        Ok(())
+    }
+
+    /// Encrypts and publishes a NIP-47 notification (kind 23196, NIP-04) to the
+    /// connected client for a received or sent payment.
+    pub async fn send_notification(
+        &self,
+        key_manager: &KeyManager,
+        notification: &WalletNotification,
+    ) -> Result<()> {
+        let client_pubkey = key_manager.user_keys().public_key();
+        let content = nip04::encrypt(
+            key_manager.server_keys().secret_key(),
+            &client_pubkey,
+            serde_json::to_string(notification)?,
+        )?;
+        let event = EventBuilder::new(
+            Kind::Custom(NOTIFICATION_KIND),
+            content,
+            [Tag::public_key(client_pubkey)],
+        )
+        .to_event(key_manager.server_keys())?;
+        self.client.send_event(event).await?;
+        Ok(())
+    }
+
+    /// Encrypts and sends a successful NIP-47 response to the requesting client.
+    pub async fn send_response(
+        &self,
+        key_manager: &KeyManager,
+        request: &Event,
+        method: Method,
+        result: serde_json::Value,
+    ) -> Result<()> {
+        let payload = json!({
+            "result_type": method.as_str(),
+            "result": result,
+        });
+        self.send_encrypted(key_manager, request, &payload).await
+    }
+
+    /// Encrypts and sends a NIP-47 error response to the requesting client.
+    pub async fn send_error_response(
+        &self,
+        key_manager: &KeyManager,
+        request: &Event,
+        code: ErrorCode,
+        message: &str,
+    ) -> Result<()> {
+        let payload = json!({
+            "result_type": "error",
+            "error": { "code": code.as_str(), "message": message },
+        });
+        self.send_encrypted(key_manager, request, &payload).await
+    }
+
+    async fn send_encrypted(
+        &self,
+        key_manager: &KeyManager,
+        request: &Event,
+        payload: &serde_json::Value,
+    ) -> Result<()> {
+        let content = nip04::encrypt(
+            key_manager.server_keys().secret_key(),
+            &request.pubkey,
+            payload.to_string(),
+        )?;
+        let event = EventBuilder::new(
+            Kind::WalletConnectResponse,
+            content,
+            [Tag::public_key(request.pubkey), Tag::event(request.id)],
+        )
+        .to_event(key_manager.server_keys())?;
+        self.client.send_event(event).await?;
+        Ok(())
+    }
+}
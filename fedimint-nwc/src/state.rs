@@ -1,16 +1,117 @@
 use std::collections::BTreeSet;
+use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::time::Duration;
 
-use nostr_sdk::{Event, EventId, JsonUtil, Kind};
-use tokio::sync::Mutex;
-use tracing::{debug, error, info};
+use governor::clock::{Clock, DefaultClock};
+use governor::state::keyed::DefaultKeyedStateStore;
+use governor::{Quota, RateLimiter};
+use nostr_sdk::{Event, EventId, JsonUtil, Kind, PublicKey};
+use prometheus::{Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry};
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+use tracing::{debug, error, info, warn};
 
+use crate::budget::BudgetManager;
 use crate::config::Cli;
 use crate::managers::KeyManager;
-use crate::nwc::{handle_nwc_request, NwcConfig};
+use crate::notifications::WalletNotification;
+use crate::nwc::{handle_nwc_request, ErrorCode, NwcConfig};
 use crate::services::{MultiMintService, NostrService};
 
+/// Per-pubkey GCRA rate limiter shared across the event loop.
+///
+/// Wraps the `governor` keyed limiter so `AppState` can keep deriving
+/// `Debug`; the limiter itself is not `Debug`, so we print an opaque marker.
+#[derive(Clone)]
+pub struct RequestLimiter(Arc<RateLimiter<PublicKey, DefaultKeyedStateStore<PublicKey>, DefaultClock>>);
+
+impl RequestLimiter {
+    /// Builds a limiter allowing `per_minute` requests per pubkey with a
+    /// `burst` allowance on top, following the GCRA/token-bucket model the
+    /// nostr relays use.
+    ///
+    /// `allow_burst` sets the absolute bucket capacity, so we pass
+    /// `per_minute + burst` to get the per-minute sustained rate *plus* the
+    /// extra burst allowance rather than letting `burst` replace the capacity.
+    ///
+    /// Note: under the current single shared connection key (see
+    /// [`crate::managers::KeyManager`]), every request carries the same author
+    /// pubkey, so this keyed limiter effectively behaves as one global bucket.
+    /// Distinct per-client buckets require per-connection secrets.
+    pub fn new(per_minute: NonZeroU32, burst: NonZeroU32) -> Self {
+        let capacity = per_minute.saturating_add(burst.get());
+        let quota = Quota::per_minute(per_minute).allow_burst(capacity);
+        Self(Arc::new(RateLimiter::keyed(quota)))
+    }
+
+    /// Checks a request from `key`, advancing the key's theoretical arrival
+    /// time when the request is permitted. Returns `Ok(())` when permitted, or `Err(wait)` with
+    /// the earliest `Duration` after which the client may retry.
+    pub fn check(&self, key: &PublicKey) -> Result<(), Duration> {
+        match self.0.check_key(key) {
+            Ok(()) => Ok(()),
+            Err(not_until) => Err(not_until.wait_time_from(DefaultClock::default().now())),
+        }
+    }
+}
+
+impl std::fmt::Debug for RequestLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestLimiter").finish_non_exhaustive()
+    }
+}
+
+/// Prometheus collectors for NWC throughput and failures.
+///
+/// All collectors are registered against a single [`Registry`] that the
+/// `/metrics` HTTP handler encodes with a `TextEncoder`. The inner collectors
+/// are cheap to clone (they are `Arc` internally), so the struct threads
+/// through `AppState` alongside the services it instruments.
+#[derive(Clone)]
+pub struct Metrics {
+    pub registry: Arc<Registry>,
+    /// Requests by method and outcome (`success`/`error`/`timeout`).
+    pub requests: IntCounterVec,
+    /// Mirrors `active_requests.len()` so operators can alert on stuck work.
+    pub active_requests: IntGauge,
+    /// `handle_nwc_request` latency in seconds.
+    pub latency: Histogram,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, anyhow::Error> {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("nwc_requests_total", "NWC requests by method and outcome"),
+            &["method", "outcome"],
+        )?;
+        let active_requests = IntGauge::new(
+            "nwc_active_requests",
+            "Number of NWC requests currently in flight",
+        )?;
+        let latency = Histogram::with_opts(HistogramOpts::new(
+            "nwc_request_duration_seconds",
+            "Latency of handle_nwc_request in seconds",
+        ))?;
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(active_requests.clone()))?;
+        registry.register(Box::new(latency.clone()))?;
+        Ok(Self {
+            registry: Arc::new(registry),
+            requests,
+            active_requests,
+            latency,
+        })
+    }
+}
+
+impl std::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metrics").finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub multimint_service: MultiMintService,
@@ -18,27 +119,77 @@ pub struct AppState {
     pub key_manager: KeyManager,
     pub active_requests: Arc<Mutex<BTreeSet<EventId>>>,
     pub nwc_config: NwcConfig,
+    pub limiter: RequestLimiter,
+    pub metrics: Metrics,
+    /// Bounds the number of requests processed concurrently.
+    pub semaphore: Arc<Semaphore>,
+    /// In-flight request tasks, drained on shutdown.
+    pub tasks: Arc<Mutex<JoinSet<()>>>,
+    /// Durable per-client spending budgets enforced on each payment.
+    pub budget: BudgetManager,
 }
 
 impl AppState {
     pub async fn new(cli: Cli) -> Result<Self, anyhow::Error> {
         let key_manager = KeyManager::new(&cli.keys_file)?;
-        let multimint_service = MultiMintService::new(cli.db_path).await?;
+        let multimint_service = MultiMintService::new(cli.db_path.clone()).await?;
         let nostr_service = NostrService::new(&key_manager, &cli.relays).await?;
 
         let active_requests = Arc::new(Mutex::new(BTreeSet::new()));
         let nwc_config = NwcConfig {
-            max_amount: cli.max_amount,
-            daily_limit: cli.daily_limit,
+            rate_limit_per_minute: cli.rate_limit_per_minute,
+            rate_limit_burst: cli.rate_limit_burst,
         };
+        let limiter = RequestLimiter::new(
+            nwc_config.rate_limit_per_minute,
+            nwc_config.rate_limit_burst,
+        );
+        let metrics = Metrics::new()?;
+        let semaphore = Arc::new(Semaphore::new(cli.max_in_flight));
+        let tasks = Arc::new(Mutex::new(JoinSet::new()));
+        let budget = BudgetManager::new(multimint_service.clone(), cli.budget_config());
 
-        Ok(Self {
+        let state = Self {
             multimint_service,
             nostr_service,
             key_manager,
             active_requests,
             nwc_config,
-        })
+            limiter,
+            metrics,
+            semaphore,
+            tasks,
+            budget,
+        };
+        state.spawn_notification_task();
+        Ok(state)
+    }
+
+    /// Consumes the wallet activity stream and pushes a NIP-47 notification to
+    /// the client for every received or sent payment, so wallets see balance
+    /// updates without polling.
+    fn spawn_notification_task(&self) {
+        let nostr_service = self.nostr_service.clone();
+        let key_manager = self.key_manager.clone();
+        let mut activity = self.multimint_service.subscribe_activity();
+        tokio::spawn(async move {
+            loop {
+                match activity.recv().await {
+                    Ok(notification) => {
+                        if let Err(e) = nostr_service
+                            .send_notification(&key_manager, &notification)
+                            .await
+                        {
+                            error!("Failed to send {} notification: {e}", notification.notification_type());
+                        }
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                        error!("Notification consumer lagged, dropped {n} events");
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
     }
 
     pub async fn init(&mut self, cli: &Cli) -> Result<(), anyhow::Error> {
@@ -48,36 +199,126 @@ impl AppState {
         Ok(())
     }
 
+    /// Drains all in-flight request tasks so Ctrl+C finishes work already
+    /// dispatched before the daemon exits. Each task is joined with
+    /// `join_next` while the `tasks` lock is held; the request tasks never
+    /// take that lock, so they can still finish and the set drains.
     pub async fn wait_for_active_requests(&self) {
-        let requests = self.active_requests.lock().await;
         loop {
-            if requests.is_empty() {
-                break;
+            let joined = {
+                let mut tasks = self.tasks.lock().await;
+                if tasks.is_empty() {
+                    break;
+                }
+                debug!("Waiting for {} requests to complete...", tasks.len());
+                tasks.join_next().await
+            };
+            match joined {
+                Some(Ok(())) => {}
+                Some(Err(e)) => error!("Request task panicked: {e}"),
+                None => break,
             }
-            debug!("Waiting for {} requests to complete...", requests.len());
-            tokio::time::sleep(Duration::from_secs(1)).await;
         }
     }
 
-    /// Adds nwc events to active requests set while waiting for them to
-    /// complete so they can finish processing before a shutdown.
+    /// Dispatches a verified nwc event onto the shared [`JoinSet`], bounded by
+    /// the semaphore so a slow request can't block the event loop or starve
+    /// other clients. Each task removes its own [`EventId`] from
+    /// `active_requests` when it finishes.
     pub async fn handle_event(&self, event: Event) {
-        if event.kind == Kind::WalletConnectRequest && event.verify().is_ok() {
-            info!("Received event: {}", event.as_json());
-            let event_id = event.id;
-            self.active_requests.lock().await.insert(event_id);
+        if event.kind != Kind::WalletConnectRequest || event.verify().is_err() {
+            error!("Invalid event: {}", event.as_json());
+            return;
+        }
+        info!("Received event: {}", event.as_json());
 
-            match tokio::time::timeout(Duration::from_secs(60), handle_nwc_request(&self, event))
+        // Throttle floods from a single client before doing any work: a
+        // compromised or buggy peer must not be able to monopolize the
+        // event loop. The limiter is keyed by the request's author pubkey.
+        if let Err(wait) = self.limiter.check(&event.pubkey) {
+            // Add a small jitter to the advertised retry window so a single
+            // client that keeps hammering can't resynchronise its retries into
+            // a tight loop that monopolizes the event loop.
+            let jitter = Duration::from_millis(rand::random::<u64>() % 500);
+            let retry_after = (wait + jitter).as_secs_f64();
+            warn!(
+                "Rate limited request from {}, retry after {retry_after:.1}s",
+                event.pubkey
+            );
+            if let Err(e) = self
+                .nostr_service
+                .send_error_response(
+                    &self.key_manager,
+                    &event,
+                    ErrorCode::RateLimited,
+                    &format!("too many requests, retry after {retry_after:.1}s"),
+                )
                 .await
             {
-                Ok(Ok(_)) => {}
-                Ok(Err(e)) => error!("Error processing request: {e}"),
-                Err(e) => error!("Timeout error: {e}"),
+                error!("Failed to send rate limit response: {e}");
+            }
+            return;
+        }
+
+        // Back-pressure: acquire a permit before accepting the request so the
+        // number of concurrent handlers stays bounded.
+        let permit = match self.semaphore.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => {
+                error!("Request semaphore closed, dropping event");
+                return;
             }
+        };
 
-            self.active_requests.lock().await.remove(&event_id);
-        } else {
-            error!("Invalid event: {}", event.as_json());
+        let event_id = event.id;
+        {
+            let mut active = self.active_requests.lock().await;
+            active.insert(event_id);
+            self.metrics.active_requests.set(active.len() as i64);
+        }
+
+        // Resolve the request method up front so the Prometheus `method`
+        // dimension is populated regardless of the eventual outcome.
+        let method = crate::nwc::request_method(self, &event);
+
+        let state = self.clone();
+        {
+            let mut tasks = self.tasks.lock().await;
+            // Reap handles of tasks that have already finished so the JoinSet
+            // doesn't grow without bound over the lifetime of the daemon.
+            while tasks.try_join_next().is_some() {}
+            tasks.spawn(async move {
+                // Hold the permit for the lifetime of the task.
+                let _permit = permit;
+
+                let timer = state.metrics.latency.start_timer();
+                let outcome = match tokio::time::timeout(
+                    Duration::from_secs(60),
+                    handle_nwc_request(&state, event),
+                )
+                .await
+                {
+                    Ok(Ok(_)) => "success",
+                    Ok(Err(e)) => {
+                        error!("Error processing request: {e}");
+                        "error"
+                    }
+                    Err(e) => {
+                        error!("Timeout error: {e}");
+                        "timeout"
+                    }
+                };
+                timer.observe_duration();
+                state
+                    .metrics
+                    .requests
+                    .with_label_values(&[method.as_str(), outcome])
+                    .inc();
+
+                let mut active = state.active_requests.lock().await;
+                active.remove(&event_id);
+                state.metrics.active_requests.set(active.len() as i64);
+            });
         }
     }
 }